@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::decode::{decode_payload, encode_payload, DEFAULT_MAX_BUFFER_SIZE};
+use crate::{DecodeError, DeviceFormat, RawBuffer};
+
+const MAGIC: &[u8; 4] = b"MTCF";
+const CONTAINER_VERSION: u32 = 1;
+
+/// Upper bound on a container's declared header size, checked before it's
+/// allocated. A corrupt or hostile file can claim any `header_size`; this
+/// keeps that claim from forcing an unbounded allocation.
+const MAX_HEADER_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum ContainerError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Buffer(DecodeError),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    UnknownDeviceId(u8),
+    MalformedHeader(String),
+    HeaderTooLarge { declared: u64, max: u64 },
+    BufferTooLarge { declared: u32, max: usize },
+}
+
+impl error::Error for ContainerError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ContainerError::Io(err) => Some(err),
+            ContainerError::Json(err) => Some(err),
+            ContainerError::Buffer(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContainerError::Io(err) => write!(f, "I/O error while accessing container: {err}"),
+            ContainerError::Json(err) => write!(f, "Malformed container header JSON: {err}"),
+            ContainerError::Buffer(err) => write!(f, "Error encoding/decoding a container buffer: {err}"),
+            ContainerError::InvalidMagic => write!(f, "File does not start with the container magic number {MAGIC:?}"),
+            ContainerError::UnsupportedVersion(version) => write!(f, "Unsupported container version {version}"),
+            ContainerError::UnknownDeviceId(id) => write!(f, "No device {id} in this container"),
+            ContainerError::MalformedHeader(msg) => write!(f, "Malformed container header: {msg}"),
+            ContainerError::HeaderTooLarge { declared, max } => write!(f, "Declared header size ({declared} bytes) exceeds the maximum allowed ({max} bytes)"),
+            ContainerError::BufferTooLarge { declared, max } => write!(f, "Declared buffer length ({declared} bytes) exceeds the maximum allowed ({max} bytes)"),
+        }
+    }
+}
+
+impl From<io::Error> for ContainerError {
+    fn from(err: io::Error) -> Self {
+        ContainerError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ContainerError {
+    fn from(err: serde_json::Error) -> Self {
+        ContainerError::Json(err)
+    }
+}
+
+impl From<DecodeError> for ContainerError {
+    fn from(err: DecodeError) -> Self {
+        ContainerError::Buffer(err)
+    }
+}
+
+/// One device's already-decoded buffers, ready to be written into a
+/// container by [`write_container`]. Buffers should already be in time
+/// order, matching every other writer in this crate (see [`crate::write_h5`]).
+pub struct DeviceStream {
+    pub format: DeviceFormat,
+    pub buffers: Vec<RawBuffer>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BufferIndexEntry {
+    offset: u64,
+    length: u32,
+}
+
+/// Writes a self-describing container to `path`: a magic number and
+/// version, then a JSON header (per-device [`DeviceFormat`] metadata plus a
+/// buffer index of offsets/lengths into the data section), then the data
+/// section itself. [`open_container`] reads the header once and seeks
+/// straight to a device's buffers afterward, without scanning the file.
+pub fn write_container(path: &Path, streams: &[DeviceStream]) -> Result<(), ContainerError> {
+    let mut data = Vec::new();
+    let mut device_entries: Vec<(&DeviceFormat, Vec<BufferIndexEntry>)> = Vec::with_capacity(streams.len());
+
+    for stream in streams {
+        let mut index = Vec::with_capacity(stream.buffers.len());
+        for buffer in &stream.buffers {
+            let payload = encode_payload(buffer, &stream.format)?;
+            index.push(BufferIndexEntry { offset: data.len() as u64, length: payload.len() as u32 });
+            data.extend_from_slice(&payload);
+        }
+        device_entries.push((&stream.format, index));
+    }
+
+    let header_bytes = serde_json::to_vec(&build_header_json(&device_entries))?;
+
+    let mut file = File::create(path)?;
+    file.write_all(MAGIC)?;
+    file.write_all(&CONTAINER_VERSION.to_be_bytes())?;
+    file.write_all(&(header_bytes.len() as u64).to_be_bytes())?;
+    file.write_all(&header_bytes)?;
+    file.write_all(&data)?;
+
+    Ok(())
+}
+
+fn build_header_json(device_entries: &[(&DeviceFormat, Vec<BufferIndexEntry>)]) -> Value {
+    let mut devices = serde_json::Map::with_capacity(device_entries.len());
+
+    for (format, index) in device_entries {
+        let buffers: Vec<Value> = index
+            .iter()
+            .map(|entry| json!({ "offset": entry.offset, "length": entry.length }))
+            .collect();
+
+        devices.insert(
+            format.id.to_string(),
+            json!({
+                "header_format": format.header_format.iter().collect::<String>(),
+                "data_format": format.data_format.iter().collect::<String>(),
+                "buffers": buffers,
+            }),
+        );
+    }
+
+    json!({ "devices": devices })
+}
+
+struct DeviceIndex {
+    format: DeviceFormat,
+    buffers: Vec<BufferIndexEntry>,
+}
+
+fn parse_header_json(header: &Value) -> Result<HashMap<u8, DeviceIndex>, ContainerError> {
+    let devices_obj = header["devices"]
+        .as_object()
+        .ok_or_else(|| ContainerError::MalformedHeader("missing 'devices' object".to_string()))?;
+
+    let mut devices = HashMap::with_capacity(devices_obj.len());
+
+    for (key, value) in devices_obj {
+        let id: u8 = key
+            .parse()
+            .map_err(|_| ContainerError::MalformedHeader(format!("device id '{key}' is not a u8")))?;
+
+        let header_format = value["header_format"]
+            .as_str()
+            .ok_or_else(|| ContainerError::MalformedHeader(format!("device {id} is missing 'header_format'")))?
+            .chars()
+            .collect();
+
+        let data_format = value["data_format"]
+            .as_str()
+            .ok_or_else(|| ContainerError::MalformedHeader(format!("device {id} is missing 'data_format'")))?
+            .chars()
+            .collect();
+
+        let buffers_array = value["buffers"]
+            .as_array()
+            .ok_or_else(|| ContainerError::MalformedHeader(format!("device {id} is missing 'buffers'")))?;
+
+        let mut buffers = Vec::with_capacity(buffers_array.len());
+        for entry in buffers_array {
+            let offset = entry["offset"]
+                .as_u64()
+                .ok_or_else(|| ContainerError::MalformedHeader(format!("device {id} has a buffer index entry missing 'offset'")))?;
+            let length = entry["length"]
+                .as_u64()
+                .ok_or_else(|| ContainerError::MalformedHeader(format!("device {id} has a buffer index entry missing 'length'")))?
+                as u32;
+            buffers.push(BufferIndexEntry { offset, length });
+        }
+
+        devices.insert(id, DeviceIndex { format: DeviceFormat { id, header_format, data_format }, buffers });
+    }
+
+    Ok(devices)
+}
+
+/// A container opened by [`open_container`]. Holds the parsed header (so
+/// [`ContainerReader::buffers`] can seek directly to a device's data
+/// without re-reading it) and the open file handle.
+pub struct ContainerReader {
+    file: File,
+    devices: HashMap<u8, DeviceIndex>,
+    data_start: u64,
+}
+
+/// Opens `path` and parses its header. The data section itself is read
+/// lazily, buffer by buffer, via [`ContainerReader::buffers`].
+pub fn open_container(path: &Path) -> Result<ContainerReader, ContainerError> {
+    let mut file = File::open(path)?;
+
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(ContainerError::InvalidMagic);
+    }
+
+    let mut version_bytes = [0u8; 4];
+    file.read_exact(&mut version_bytes)?;
+    let version = u32::from_be_bytes(version_bytes);
+    if version != CONTAINER_VERSION {
+        return Err(ContainerError::UnsupportedVersion(version));
+    }
+
+    let mut header_size_bytes = [0u8; 8];
+    file.read_exact(&mut header_size_bytes)?;
+    let header_size = u64::from_be_bytes(header_size_bytes);
+    if header_size > MAX_HEADER_SIZE {
+        return Err(ContainerError::HeaderTooLarge { declared: header_size, max: MAX_HEADER_SIZE });
+    }
+
+    let mut header_bytes = vec![0u8; header_size as usize];
+    file.read_exact(&mut header_bytes)?;
+    let header_json: Value = serde_json::from_slice(&header_bytes)?;
+
+    let devices = parse_header_json(&header_json)?;
+    let data_start = 4 + 4 + 8 + header_size;
+
+    Ok(ContainerReader { file, devices, data_start })
+}
+
+impl ContainerReader {
+    /// Seeks directly to `device_id`'s buffers via the on-disk index and
+    /// decodes them, without reading any other device's data.
+    pub fn buffers(&mut self, device_id: u8) -> Result<Vec<RawBuffer>, ContainerError> {
+        let device = self
+            .devices
+            .get(&device_id)
+            .ok_or(ContainerError::UnknownDeviceId(device_id))?;
+
+        let mut out = Vec::with_capacity(device.buffers.len());
+        for entry in &device.buffers {
+            if entry.length as usize > DEFAULT_MAX_BUFFER_SIZE {
+                return Err(ContainerError::BufferTooLarge { declared: entry.length, max: DEFAULT_MAX_BUFFER_SIZE });
+            }
+
+            let payload_offset = self.data_start + entry.offset;
+            self.file.seek(SeekFrom::Start(payload_offset))?;
+            let mut payload = vec![0u8; entry.length as usize];
+            self.file.read_exact(&mut payload)?;
+            out.push(decode_payload(payload, &device.format, payload_offset)?);
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sample;
+    use std::env;
+
+    fn format(id: u8) -> DeviceFormat {
+        DeviceFormat {
+            id,
+            header_format: "<BI".chars().collect(),
+            data_format: "<f".chars().collect(),
+        }
+    }
+
+    fn temp_container_path(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("tag_deserializer_test_{name}_{}.mtcf", std::process::id()))
+    }
+
+    #[test]
+    fn container_round_trips_buffers_by_device() {
+        let path = temp_container_path("round_trip");
+
+        let streams = vec![
+            DeviceStream {
+                format: format(1),
+                buffers: vec![
+                    RawBuffer { id: 1, time: 10, samples: vec![Sample::F32(1.5)] },
+                    RawBuffer { id: 1, time: 20, samples: vec![Sample::F32(2.5)] },
+                ],
+            },
+            DeviceStream {
+                format: format(2),
+                buffers: vec![RawBuffer { id: 2, time: 15, samples: vec![Sample::F32(3.5)] }],
+            },
+        ];
+
+        write_container(&path, &streams).unwrap();
+
+        let mut reader = open_container(&path).unwrap();
+        let device1 = reader.buffers(1).unwrap();
+        let device2 = reader.buffers(2).unwrap();
+
+        assert_eq!(device1.len(), 2);
+        assert_eq!(device1[0].time, 10);
+        assert_eq!(device1[1].time, 20);
+        assert_eq!(device1[0].samples, vec![Sample::F32(1.5)]);
+
+        assert_eq!(device2.len(), 1);
+        assert_eq!(device2[0].time, 15);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_container_rejects_bad_magic() {
+        let path = temp_container_path("bad_magic");
+        std::fs::write(&path, b"NOPE0000").unwrap();
+
+        let result = open_container(&path);
+
+        assert!(matches!(result, Err(ContainerError::InvalidMagic)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_container_rejects_header_size_over_cap() {
+        let path = temp_container_path("header_too_large");
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&CONTAINER_VERSION.to_be_bytes());
+        bytes.extend_from_slice(&(MAX_HEADER_SIZE + 1).to_be_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+
+        let result = open_container(&path);
+
+        assert!(matches!(result, Err(ContainerError::HeaderTooLarge { .. })));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,695 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use crate::error::eof_or_io;
+use crate::{DecodeError, DeviceFormat, RawBuffer};
+
+/// Byte order selector for the `<`/`>` prefix on a format string.
+///
+/// MTAG capture files are little-endian by default, matching the tag's
+/// native C++ struct layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// A single decoded value, tagged with the `struct`-style format char it
+/// came from so downstream consumers don't lose type information.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sample {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    F64(f64),
+    I64(i64),
+}
+
+/// Size in bytes of a single Python `struct`-style format character.
+pub(crate) fn format_char_size(ch: char, offset: u64) -> Result<usize, DecodeError> {
+    match ch {
+        'B' => Ok(1),
+        'H' => Ok(2),
+        'I' => Ok(4),
+        'i' => Ok(4),
+        'f' => Ok(4),
+        'd' => Ok(8),
+        'q' => Ok(8),
+        _ => Err(DecodeError::UnknownFormatChar { offset, ch }),
+    }
+}
+
+/// Strips a leading `<`/`>` endian marker, defaulting to little-endian.
+pub(crate) fn parse_endian(format: &[char]) -> (Endian, &[char]) {
+    match format.first() {
+        Some('<') => (Endian::Little, &format[1..]),
+        Some('>') => (Endian::Big, &format[1..]),
+        _ => (Endian::Little, format),
+    }
+}
+
+/// Reads one scalar off `reader`, advancing `offset` by however many bytes
+/// it consumed.
+fn read_scalar(ch: char, endian: Endian, reader: &mut impl Read, offset: &mut u64) -> Result<Sample, DecodeError> {
+    macro_rules! read_int {
+        ($ty:ty, $variant:ident) => {{
+            let mut buf = [0u8; std::mem::size_of::<$ty>()];
+            reader.read_exact(&mut buf).map_err(|err| eof_or_io(err, *offset))?;
+            *offset += buf.len() as u64;
+            let val = match endian {
+                Endian::Little => <$ty>::from_le_bytes(buf),
+                Endian::Big => <$ty>::from_be_bytes(buf),
+            };
+            Sample::$variant(val)
+        }};
+    }
+
+    Ok(match ch {
+        'B' => read_int!(u8, U8),
+        'H' => read_int!(u16, U16),
+        'I' => read_int!(u32, U32),
+        'i' => read_int!(i32, I32),
+        'f' => read_int!(f32, F32),
+        'd' => read_int!(f64, F64),
+        'q' => read_int!(i64, I64),
+        _ => return Err(DecodeError::UnknownFormatChar { offset: *offset, ch }),
+    })
+}
+
+fn sample_as_u8(sample: Sample) -> u8 {
+    match sample {
+        Sample::U8(v) => v,
+        Sample::U16(v) => v as u8,
+        Sample::U32(v) => v as u8,
+        Sample::I32(v) => v as u8,
+        Sample::F32(v) => v as u8,
+        Sample::F64(v) => v as u8,
+        Sample::I64(v) => v as u8,
+    }
+}
+
+fn sample_as_u32(sample: Sample) -> u32 {
+    match sample {
+        Sample::U8(v) => v as u32,
+        Sample::U16(v) => v as u32,
+        Sample::U32(v) => v,
+        Sample::I32(v) => v as u32,
+        Sample::F32(v) => v as u32,
+        Sample::F64(v) => v as u32,
+        Sample::I64(v) => v as u32,
+    }
+}
+
+/// Builds a [`Sample`] of whatever type `ch` denotes, holding `value`. Used
+/// to re-encode the `id`/`time` header fields, which are tracked as plain
+/// integers rather than [`Sample`]s.
+fn sample_with_value(ch: char, value: u32) -> Result<Sample, DecodeError> {
+    Ok(match ch {
+        'B' => Sample::U8(value as u8),
+        'H' => Sample::U16(value as u16),
+        'I' => Sample::U32(value),
+        'i' => Sample::I32(value as i32),
+        'f' => Sample::F32(value as f32),
+        'd' => Sample::F64(value as f64),
+        'q' => Sample::I64(value as i64),
+        _ => return Err(DecodeError::UnknownFormatChar { offset: 0, ch }),
+    })
+}
+
+fn zero_sample(ch: char) -> Result<Sample, DecodeError> {
+    sample_with_value(ch, 0)
+}
+
+fn write_sample(sample: Sample, endian: Endian, writer: &mut impl io::Write) -> Result<(), DecodeError> {
+    macro_rules! write_bytes {
+        ($v:expr) => {{
+            let bytes = match endian {
+                Endian::Little => $v.to_le_bytes(),
+                Endian::Big => $v.to_be_bytes(),
+            };
+            writer.write_all(&bytes)?;
+        }};
+    }
+
+    match sample {
+        Sample::U8(v) => write_bytes!(v),
+        Sample::U16(v) => write_bytes!(v),
+        Sample::U32(v) => write_bytes!(v),
+        Sample::I32(v) => write_bytes!(v),
+        Sample::F32(v) => write_bytes!(v),
+        Sample::F64(v) => write_bytes!(v),
+        Sample::I64(v) => write_bytes!(v),
+    }
+
+    Ok(())
+}
+
+/// Decodes `header_format` off the front of `reader`, returning the
+/// buffer's `id` (first field) and `time` (second field). Any further
+/// header fields are decoded to keep the reader aligned but otherwise
+/// discarded. `offset` is the running file offset, advanced as bytes are
+/// consumed so any error reports exactly where in the file it happened.
+fn decode_header(
+    reader: &mut impl Read,
+    header_format: &[char],
+    offset: &mut u64,
+) -> Result<(u8, u32), DecodeError> {
+    let (endian, chars) = parse_endian(header_format);
+    let mut id: Option<u8> = None;
+    let mut time: Option<u32> = None;
+
+    for &ch in chars {
+        let sample = read_scalar(ch, endian, reader, offset)?;
+        if id.is_none() {
+            id = Some(sample_as_u8(sample));
+        } else if time.is_none() {
+            time = Some(sample_as_u32(sample));
+        }
+    }
+
+    Ok((id.unwrap_or(0), time.unwrap_or(0)))
+}
+
+/// Reads the `u32` length prefix in front of a buffer's payload, then the
+/// payload itself, advancing `offset` by however many bytes were consumed.
+/// Returns `Ok(None)` only if the reader is exhausted right at a frame
+/// boundary (zero bytes available before a new prefix starts); a prefix
+/// that starts but can't be fully read (1-3 stray trailing bytes) is a
+/// truncated file, not a clean end, so it's reported as
+/// [`DecodeError::UnexpectedEof`] rather than silently dropped — matching
+/// [`BufferStream`]'s handling of the same "leftover-but-incomplete" case.
+/// Any other I/O failure (including a truncated payload) is also an error.
+/// A declared length over `max_buffer_size` is rejected before it's
+/// allocated, so a corrupt length prefix can't force an unbounded
+/// allocation.
+fn read_length_prefixed_payload(
+    reader: &mut impl Read,
+    offset: &mut u64,
+    max_buffer_size: usize,
+) -> Result<Option<Vec<u8>>, DecodeError> {
+    let mut len_bytes = [0u8; 4];
+    let mut filled = 0usize;
+    while filled < len_bytes.len() {
+        match reader.read(&mut len_bytes[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    if filled == 0 {
+        return Ok(None);
+    }
+    if filled < len_bytes.len() {
+        return Err(DecodeError::UnexpectedEof { offset: *offset + filled as u64 });
+    }
+    *offset += 4;
+
+    let payload_len = u32::from_le_bytes(len_bytes) as usize;
+    if payload_len > max_buffer_size {
+        return Err(DecodeError::BufferLengthExceedsCap { offset: *offset, len: payload_len, cap: max_buffer_size });
+    }
+
+    let mut payload = vec![0u8; payload_len];
+    reader.read_exact(&mut payload).map_err(|err| eof_or_io(err, *offset))?;
+    *offset += payload_len as u64;
+
+    Ok(Some(payload))
+}
+
+/// Decodes an already-read payload, whose first byte sits at file offset
+/// `payload_offset`: `format.header_format` recovers `id` and `time`, then
+/// `format.data_format` is applied repeatedly over whatever bytes remain to
+/// produce the buffer's [`Sample`]s.
+pub(crate) fn decode_payload(
+    payload: Vec<u8>,
+    format: &DeviceFormat,
+    payload_offset: u64,
+) -> Result<RawBuffer, DecodeError> {
+    let payload_len = payload.len();
+    let mut cursor = io::Cursor::new(payload);
+    let mut offset = payload_offset;
+
+    let (id, time) = decode_header(&mut cursor, &format.header_format, &mut offset)?;
+
+    let (endian, data_chars) = parse_endian(&format.data_format);
+    let record_size: usize = data_chars
+        .iter()
+        .map(|&ch| format_char_size(ch, offset))
+        .sum::<Result<usize, _>>()?;
+
+    let consumed = cursor.position() as usize;
+    let remaining = payload_len.saturating_sub(consumed);
+
+    // A device with an empty `data_format` (header-only/heartbeat buffers)
+    // has a record_size of 0; that's only an error if there are leftover
+    // bytes it can't account for.
+    let record_count = match record_size {
+        0 if remaining == 0 => 0,
+        0 => return Err(DecodeError::BufferLengthNotMultiple { offset, len: remaining, record_size }),
+        n if !remaining.is_multiple_of(n) => {
+            return Err(DecodeError::BufferLengthNotMultiple { offset, len: remaining, record_size })
+        }
+        n => remaining / n,
+    };
+    let mut samples = Vec::with_capacity(record_count * data_chars.len());
+    for _ in 0..record_count {
+        for &ch in data_chars {
+            samples.push(read_scalar(ch, endian, &mut cursor, &mut offset)?);
+        }
+    }
+
+    Ok(RawBuffer { id, time, samples })
+}
+
+/// Inverse of [`decode_payload`]: re-encodes `buffer` into the same raw
+/// payload layout `format` describes, so it can be written back out (e.g.
+/// into a container's data section). `format.header_format`'s first two
+/// fields get `buffer.id` and `buffer.time`; any further header fields are
+/// written as zero since [`RawBuffer`] doesn't retain them.
+pub(crate) fn encode_payload(buffer: &RawBuffer, format: &DeviceFormat) -> Result<Vec<u8>, DecodeError> {
+    let mut payload = Vec::new();
+    let (header_endian, header_chars) = parse_endian(&format.header_format);
+
+    for (i, &ch) in header_chars.iter().enumerate() {
+        let sample = match i {
+            0 => sample_with_value(ch, buffer.id as u32)?,
+            1 => sample_with_value(ch, buffer.time)?,
+            _ => zero_sample(ch)?,
+        };
+        write_sample(sample, header_endian, &mut payload)?;
+    }
+
+    let (data_endian, _) = parse_endian(&format.data_format);
+    for &sample in &buffer.samples {
+        write_sample(sample, data_endian, &mut payload)?;
+    }
+
+    Ok(payload)
+}
+
+/// Decodes one length-prefixed buffer from `reader` according to `format`.
+///
+/// The wire layout is a little-endian `u32` payload length, followed by
+/// that many bytes of payload.
+pub fn decode_buffer(reader: &mut impl Read, format: &DeviceFormat) -> Result<RawBuffer, DecodeError> {
+    let mut offset = 0u64;
+    let payload = read_length_prefixed_payload(reader, &mut offset, DEFAULT_MAX_BUFFER_SIZE)?
+        .ok_or(DecodeError::UnexpectedEof { offset })?;
+    let payload_offset = offset - payload.len() as u64;
+    decode_payload(payload, format, payload_offset)
+}
+
+/// Decodes every length-prefixed buffer in `reader`, dispatching each one
+/// to the right [`DeviceFormat`] by `id`.
+///
+/// All devices are assumed to share the same `header_format` (the common
+/// framing every buffer starts with), so an arbitrary entry in `formats`
+/// is used to peek the `id` before the matching format's `data_format`
+/// decodes the rest of the payload. The result is sorted in time order
+/// (see `RawBuffer`'s `Ord`), ready for a writer like [`crate::write_h5`].
+pub fn decode_all(
+    reader: &mut impl Read,
+    formats: &HashMap<u8, DeviceFormat>,
+) -> Result<Vec<RawBuffer>, DecodeError> {
+    let header_format = formats
+        .values()
+        .next()
+        .map(|format| format.header_format.clone())
+        .unwrap_or_default();
+
+    let mut buffers = Vec::new();
+    let mut offset = 0u64;
+
+    while let Some(payload) = read_length_prefixed_payload(reader, &mut offset, DEFAULT_MAX_BUFFER_SIZE)? {
+        let payload_offset = offset - payload.len() as u64;
+        let mut peek_offset = payload_offset;
+        let (id, _time) = decode_header(&mut io::Cursor::new(&payload[..]), &header_format, &mut peek_offset)?;
+        let format = formats
+            .get(&id)
+            .ok_or(DecodeError::UnknownDeviceId { offset: payload_offset, id })?;
+        buffers.push(decode_payload(payload, format, payload_offset)?);
+    }
+
+    buffers.sort();
+    Ok(buffers)
+}
+
+/// Default cap on [`BufferStream`]'s working buffer, in bytes. Chosen to
+/// comfortably hold a handful of buffers at once without letting a
+/// multi-gigabyte capture file's worth of data pile up in RAM.
+pub const DEFAULT_MAX_BUFFER_SIZE: usize = 64 * 1024 * 1024;
+
+/// How many bytes [`BufferStream`] reads from its source at a time while
+/// topping up the working buffer.
+const FILL_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Decodes `reader` lazily, one [`RawBuffer`] at a time, keeping only a
+/// bounded working buffer in memory regardless of how large the underlying
+/// capture file is. Use this in place of [`decode_all`] for multi-gigabyte
+/// captures where materializing every buffer up front isn't an option.
+///
+/// Equivalent to [`decode_stream_with_capacity`] with [`DEFAULT_MAX_BUFFER_SIZE`].
+pub fn decode_stream<R: Read>(reader: R, formats: HashMap<u8, DeviceFormat>) -> BufferStream<R> {
+    decode_stream_with_capacity(reader, formats, DEFAULT_MAX_BUFFER_SIZE)
+}
+
+/// Same as [`decode_stream`], but with a caller-chosen cap on the working
+/// buffer. A single length-prefixed buffer whose declared length would
+/// exceed `max_buffer_size` is reported as
+/// [`DecodeError::BufferLengthExceedsCap`] rather than being read into
+/// memory.
+pub fn decode_stream_with_capacity<R: Read>(
+    reader: R,
+    formats: HashMap<u8, DeviceFormat>,
+    max_buffer_size: usize,
+) -> BufferStream<R> {
+    let header_format = formats
+        .values()
+        .next()
+        .map(|format| format.header_format.clone())
+        .unwrap_or_default();
+
+    BufferStream {
+        reader,
+        formats,
+        header_format,
+        buf: Vec::new(),
+        max_buffer_size,
+        offset: 0,
+        done: false,
+    }
+}
+
+/// Iterator returned by [`decode_stream`]/[`decode_stream_with_capacity`].
+///
+/// Modeled on a classic codec `decode()` loop: top up a working buffer from
+/// the source, decode as many complete frames out of it as possible,
+/// dropping the consumed prefix after each one, and ask for more bytes once
+/// what's left isn't enough for another frame. `offset` tracks the total
+/// number of bytes consumed from the source so far, so any error names the
+/// file offset it was found at. `done` latches once the source is
+/// exhausted or a decode error has been yielded, so the iterator correctly
+/// stops after reporting one error instead of looping forever.
+pub struct BufferStream<R> {
+    reader: R,
+    formats: HashMap<u8, DeviceFormat>,
+    header_format: Vec<char>,
+    buf: Vec<u8>,
+    max_buffer_size: usize,
+    offset: u64,
+    done: bool,
+}
+
+impl<R: Read> BufferStream<R> {
+    /// Tries to decode one complete frame off the front of `buf`.
+    ///
+    /// Returns `Ok(None)` when `buf` doesn't yet hold a full frame (the
+    /// caller should top it up and retry), or an error if the frame is
+    /// malformed or declares a length past `max_buffer_size`.
+    fn try_decode_one(&mut self) -> Result<Option<RawBuffer>, DecodeError> {
+        if self.buf.len() < 4 {
+            return Ok(None);
+        }
+
+        let payload_len = u32::from_le_bytes(self.buf[0..4].try_into().unwrap()) as usize;
+        if payload_len > self.max_buffer_size {
+            return Err(DecodeError::BufferLengthExceedsCap {
+                offset: self.offset,
+                len: payload_len,
+                cap: self.max_buffer_size,
+            });
+        }
+
+        let frame_len = 4 + payload_len;
+        if self.buf.len() < frame_len {
+            return Ok(None);
+        }
+
+        let payload = self.buf[4..frame_len].to_vec();
+        self.buf.drain(0..frame_len);
+
+        let payload_offset = self.offset + 4;
+        self.offset += frame_len as u64;
+
+        let mut peek_offset = payload_offset;
+        let (id, _time) = decode_header(&mut io::Cursor::new(&payload[..]), &self.header_format, &mut peek_offset)?;
+        let format = self
+            .formats
+            .get(&id)
+            .ok_or(DecodeError::UnknownDeviceId { offset: payload_offset, id })?;
+        decode_payload(payload, format, payload_offset).map(Some)
+    }
+
+    /// Reads up to [`FILL_CHUNK_SIZE`] more bytes from the source into `buf`.
+    /// Returns `Ok(false)` once the source is exhausted.
+    fn fill_buf(&mut self) -> Result<bool, DecodeError> {
+        let mut chunk = [0u8; FILL_CHUNK_SIZE];
+        let n = self.reader.read(&mut chunk)?;
+        if n == 0 {
+            return Ok(false);
+        }
+        self.buf.extend_from_slice(&chunk[..n]);
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for BufferStream<R> {
+    type Item = Result<RawBuffer, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            match self.try_decode_one() {
+                Ok(Some(buffer)) => return Some(Ok(buffer)),
+                Ok(None) => {}
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+
+            match self.fill_buf() {
+                Ok(true) => continue,
+                Ok(false) => {
+                    self.done = true;
+                    if self.buf.is_empty() {
+                        return None;
+                    }
+                    return Some(Err(DecodeError::UnexpectedEof { offset: self.offset }));
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_endian_defaults_to_little() {
+        let format: Vec<char> = "BI".chars().collect();
+        let (endian, rest) = parse_endian(&format);
+        assert_eq!(endian, Endian::Little);
+        assert_eq!(rest, &['B', 'I']);
+    }
+
+    #[test]
+    fn parse_endian_reads_big_marker() {
+        let format: Vec<char> = ">BI".chars().collect();
+        let (endian, rest) = parse_endian(&format);
+        assert_eq!(endian, Endian::Big);
+        assert_eq!(rest, &['B', 'I']);
+    }
+
+    #[test]
+    fn decode_buffer_reads_header_and_samples() {
+        let format = DeviceFormat {
+            id: 1,
+            header_format: "<BI".chars().collect(),
+            data_format: "<f".chars().collect(),
+        };
+
+        // id=1, time=42, two f32 samples (1.5, 2.5)
+        let mut bytes: Vec<u8> = Vec::new();
+        let payload_len: u32 = 1 + 4 + 4 + 4;
+        bytes.extend_from_slice(&payload_len.to_le_bytes());
+        bytes.push(1u8);
+        bytes.extend_from_slice(&42u32.to_le_bytes());
+        bytes.extend_from_slice(&1.5f32.to_le_bytes());
+        bytes.extend_from_slice(&2.5f32.to_le_bytes());
+
+        let mut reader = io::Cursor::new(bytes);
+        let buffer = decode_buffer(&mut reader, &format).unwrap();
+
+        assert_eq!(buffer.id, 1);
+        assert_eq!(buffer.time, 42);
+        assert_eq!(buffer.samples, vec![Sample::F32(1.5), Sample::F32(2.5)]);
+    }
+
+    #[test]
+    fn decode_stream_yields_buffers_in_arrival_order() {
+        let format = DeviceFormat {
+            id: 1,
+            header_format: "<BI".chars().collect(),
+            data_format: "<f".chars().collect(),
+        };
+        let mut formats = HashMap::new();
+        formats.insert(1u8, format);
+
+        let mut bytes: Vec<u8> = Vec::new();
+        for (time, sample) in [(42u32, 1.5f32), (7u32, 2.5f32)] {
+            let payload_len: u32 = 1 + 4 + 4;
+            bytes.extend_from_slice(&payload_len.to_le_bytes());
+            bytes.push(1u8);
+            bytes.extend_from_slice(&time.to_le_bytes());
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        let reader = io::Cursor::new(bytes);
+        let buffers: Result<Vec<RawBuffer>, DecodeError> = decode_stream(reader, formats).collect();
+        let buffers = buffers.unwrap();
+
+        assert_eq!(buffers.len(), 2);
+        assert_eq!(buffers[0].time, 42);
+        assert_eq!(buffers[1].time, 7);
+    }
+
+    #[test]
+    fn decode_stream_rejects_buffer_over_cap() {
+        let format = DeviceFormat {
+            id: 1,
+            header_format: "<BI".chars().collect(),
+            data_format: "<f".chars().collect(),
+        };
+        let mut formats = HashMap::new();
+        formats.insert(1u8, format);
+
+        let mut bytes: Vec<u8> = Vec::new();
+        let payload_len: u32 = 1 + 4 + 4;
+        bytes.extend_from_slice(&payload_len.to_le_bytes());
+        bytes.push(1u8);
+        bytes.extend_from_slice(&42u32.to_le_bytes());
+        bytes.extend_from_slice(&1.5f32.to_le_bytes());
+
+        let reader = io::Cursor::new(bytes);
+        let mut stream = decode_stream_with_capacity(reader, formats, 4);
+
+        assert!(matches!(
+            stream.next(),
+            Some(Err(DecodeError::BufferLengthExceedsCap { .. }))
+        ));
+    }
+
+    #[test]
+    fn decode_buffer_rejects_partial_record() {
+        let format = DeviceFormat {
+            id: 1,
+            header_format: "<BI".chars().collect(),
+            data_format: "<f".chars().collect(),
+        };
+
+        let mut bytes: Vec<u8> = Vec::new();
+        let payload_len: u32 = 1 + 4 + 2; // two trailing bytes short of a full f32
+        bytes.extend_from_slice(&payload_len.to_le_bytes());
+        bytes.push(1u8);
+        bytes.extend_from_slice(&42u32.to_le_bytes());
+        bytes.extend_from_slice(&[0u8, 0u8]);
+
+        let mut reader = io::Cursor::new(bytes);
+        let result = decode_buffer(&mut reader, &format);
+
+        assert!(matches!(result, Err(DecodeError::BufferLengthNotMultiple { .. })));
+    }
+
+    #[test]
+    fn decode_buffer_reports_offset_on_unknown_format_char() {
+        let format = DeviceFormat {
+            id: 1,
+            header_format: "<BI".chars().collect(),
+            data_format: "<z".chars().collect(),
+        };
+
+        let mut bytes: Vec<u8> = Vec::new();
+        let payload_len: u32 = 1 + 4;
+        bytes.extend_from_slice(&payload_len.to_le_bytes());
+        bytes.push(1u8);
+        bytes.extend_from_slice(&42u32.to_le_bytes());
+
+        let mut reader = io::Cursor::new(bytes);
+        let result = decode_buffer(&mut reader, &format);
+
+        // The length prefix (4 bytes) plus the 5-byte header land the
+        // record-size check at file offset 9.
+        assert!(matches!(
+            result,
+            Err(DecodeError::UnknownFormatChar { offset: 9, ch: 'z' })
+        ));
+    }
+
+    #[test]
+    fn decode_payload_allows_header_only_buffer_with_empty_data_format() {
+        let format = DeviceFormat {
+            id: 1,
+            header_format: "<BI".chars().collect(),
+            data_format: Vec::new(),
+        };
+
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.push(1u8);
+        bytes.extend_from_slice(&42u32.to_le_bytes());
+
+        let buffer = decode_payload(bytes, &format, 0).unwrap();
+
+        assert_eq!(buffer.id, 1);
+        assert_eq!(buffer.time, 42);
+        assert!(buffer.samples.is_empty());
+    }
+
+    #[test]
+    fn read_length_prefixed_payload_rejects_len_over_cap() {
+        let mut bytes: Vec<u8> = Vec::new();
+        let payload_len: u32 = 100;
+        bytes.extend_from_slice(&payload_len.to_le_bytes());
+
+        let mut reader = io::Cursor::new(bytes);
+        let mut offset = 0u64;
+        let result = read_length_prefixed_payload(&mut reader, &mut offset, 10);
+
+        assert!(matches!(result, Err(DecodeError::BufferLengthExceedsCap { .. })));
+    }
+
+    #[test]
+    fn decode_all_errors_on_truncated_trailing_length_prefix() {
+        let format = DeviceFormat {
+            id: 1,
+            header_format: "<BI".chars().collect(),
+            data_format: "<f".chars().collect(),
+        };
+        let mut formats = HashMap::new();
+        formats.insert(1u8, format);
+
+        let mut bytes: Vec<u8> = Vec::new();
+        let payload_len: u32 = 1 + 4 + 4;
+        bytes.extend_from_slice(&payload_len.to_le_bytes());
+        bytes.push(1u8);
+        bytes.extend_from_slice(&42u32.to_le_bytes());
+        bytes.extend_from_slice(&1.5f32.to_le_bytes());
+        bytes.extend_from_slice(&[0u8, 0u8]); // 2 stray bytes, not a full length prefix
+
+        let mut reader = io::Cursor::new(bytes);
+        let result = decode_all(&mut reader, &formats);
+
+        assert!(matches!(result, Err(DecodeError::UnexpectedEof { .. })));
+    }
+}
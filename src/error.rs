@@ -0,0 +1,52 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::HEADER_MAX_SIZE;
+
+/// Crate-wide decode error. Supersedes the separate `HeaderError` enum
+/// this crate used to have, so every header- and buffer-level failure is reported through one type, and every
+/// format/length error carries the byte offset into the source file where
+/// it was found.
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("Cannot find line to use as the header")]
+    HeaderMissing,
+
+    #[error("First line of file ({0} characters) exceeds the maximum possible length of the header ({HEADER_MAX_SIZE} characters)")]
+    ReadLineExceedsSize(usize),
+
+    #[error("Malformed 'buffers' entry in header: {0}")]
+    MalformedBuffers(String),
+
+    #[error("At byte offset {offset}: unknown struct format character '{ch}'")]
+    UnknownFormatChar { offset: u64, ch: char },
+
+    #[error("At byte offset {offset}: buffer length ({len} bytes) is not a multiple of the record size ({record_size} bytes)")]
+    BufferLengthNotMultiple { offset: u64, len: usize, record_size: usize },
+
+    #[error("At byte offset {offset}: no DeviceFormat known for device id {id}")]
+    UnknownDeviceId { offset: u64, id: u8 },
+
+    #[error("At byte offset {offset}: declared buffer length ({len} bytes) exceeds the maximum allowed buffer size ({cap} bytes)")]
+    BufferLengthExceedsCap { offset: u64, len: usize, cap: usize },
+
+    #[error("At byte offset {offset}: unexpected end of file")]
+    UnexpectedEof { offset: u64 },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Converts a failed read into [`DecodeError::UnexpectedEof`] (at `offset`)
+/// when it's a clean EOF, or [`DecodeError::Io`] otherwise.
+pub(crate) fn eof_or_io(err: io::Error, offset: u64) -> DecodeError {
+    if err.kind() == io::ErrorKind::UnexpectedEof {
+        DecodeError::UnexpectedEof { offset }
+    } else {
+        DecodeError::Io(err)
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use hdf5::types::VarLenUnicode;
+
+use crate::decode::parse_endian;
+use crate::{DeviceFormat, RawBuffer, Sample};
+
+/// Number of records buffered per device before they're flushed to their
+/// HDF5 datasets. Keeps memory use bounded to a few devices' worth of
+/// chunks rather than the whole capture.
+const FLUSH_BATCH_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub enum Hdf5WriteError {
+    Io(io::Error),
+    Hdf5(hdf5::Error),
+    UnknownDevice(u8),
+    /// Internal failures with no underlying typed error to wrap (e.g. an
+    /// unsupported format character).
+    Message(String),
+}
+
+impl error::Error for Hdf5WriteError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Hdf5WriteError::Io(err) => Some(err),
+            Hdf5WriteError::Hdf5(err) => Some(err),
+            Hdf5WriteError::UnknownDevice(_) | Hdf5WriteError::Message(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Hdf5WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Hdf5WriteError::Io(err) => write!(f, "I/O error while writing HDF5 output: {err}"),
+            Hdf5WriteError::Hdf5(err) => write!(f, "HDF5 error: {err}"),
+            Hdf5WriteError::UnknownDevice(id) => write!(f, "No DeviceFormat supplied for device id {id}"),
+            Hdf5WriteError::Message(msg) => write!(f, "HDF5 error: {msg}"),
+        }
+    }
+}
+
+impl From<io::Error> for Hdf5WriteError {
+    fn from(err: io::Error) -> Self {
+        Hdf5WriteError::Io(err)
+    }
+}
+
+impl From<hdf5::Error> for Hdf5WriteError {
+    fn from(err: hdf5::Error) -> Self {
+        Hdf5WriteError::Hdf5(err)
+    }
+}
+
+/// Writes `header_json` as a root attribute, then streams `buffers` (which
+/// must already be in time order, per [`RawBuffer`]'s `Ord`) into one HDF5
+/// group per device id, with a dataset per `DeviceFormat` field plus
+/// `time`. Buffers are accumulated per device only up to
+/// [`FLUSH_BATCH_SIZE`] records before being appended to disk, so a
+/// multi-gigabyte capture never has to fit in RAM at once.
+pub fn write_h5<I>(
+    path: &Path,
+    header_json: &str,
+    formats: &HashMap<u8, DeviceFormat>,
+    buffers: I,
+) -> Result<(), Hdf5WriteError>
+where
+    I: IntoIterator<Item = RawBuffer>,
+{
+    let file = hdf5::File::create(path)?;
+    write_header_attr(&file, header_json)?;
+
+    let mut devices: HashMap<u8, DeviceColumns> = HashMap::new();
+
+    for buffer in buffers {
+        let columns = match devices.get_mut(&buffer.id) {
+            Some(columns) => columns,
+            None => {
+                let format = formats
+                    .get(&buffer.id)
+                    .ok_or(Hdf5WriteError::UnknownDevice(buffer.id))?;
+                let group = file.create_group(&format!("device_{}", buffer.id))?;
+                let (_, data_chars) = parse_endian(&format.data_format);
+                devices.insert(buffer.id, DeviceColumns::new(group, data_chars.to_vec()));
+                devices.get_mut(&buffer.id).unwrap()
+            }
+        };
+
+        columns.push_buffer(&buffer)?;
+    }
+
+    for columns in devices.values_mut() {
+        columns.flush()?;
+    }
+
+    Ok(())
+}
+
+fn write_header_attr(file: &hdf5::File, header_json: &str) -> Result<(), Hdf5WriteError> {
+    let value: VarLenUnicode = header_json
+        .parse()
+        .map_err(|_| Hdf5WriteError::Message("header JSON is not valid unicode".to_string()))?;
+
+    file.new_attr::<VarLenUnicode>()
+        .create("header")?
+        .write_scalar(&value)?;
+
+    Ok(())
+}
+
+/// Per-device, per-field record accumulator. Kept free of any HDF5 state so
+/// its batching bookkeeping (including the header-only/empty-`data_format`
+/// case) can be unit tested without a live HDF5 install; [`DeviceColumns`]
+/// pairs it with the `hdf5::Group` it's flushed to.
+struct ColumnBuffer {
+    field_chars: Vec<char>,
+    time: Vec<u32>,
+    fields: Vec<Vec<Sample>>,
+}
+
+impl ColumnBuffer {
+    fn new(field_chars: Vec<char>) -> Self {
+        let fields = field_chars.iter().map(|_| Vec::new()).collect();
+        ColumnBuffer { field_chars, time: Vec::new(), fields }
+    }
+
+    /// Buffers one [`RawBuffer`]'s records. A device with an empty
+    /// `data_format` (header-only/heartbeat buffers) still gets one `time`
+    /// row per buffer, even though it has no per-field samples to go with
+    /// it.
+    fn push_buffer(&mut self, buffer: &RawBuffer) {
+        let field_count = self.field_chars.len();
+        if field_count == 0 {
+            self.time.push(buffer.time);
+            return;
+        }
+
+        for record in buffer.samples.chunks(field_count) {
+            self.time.push(buffer.time);
+            for (field, sample) in self.fields.iter_mut().zip(record.iter()) {
+                field.push(*sample);
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.time.clear();
+        for field in &mut self.fields {
+            field.clear();
+        }
+    }
+}
+
+/// Per-device HDF5 sink, flushed to its datasets every [`FLUSH_BATCH_SIZE`]
+/// records.
+struct DeviceColumns {
+    group: hdf5::Group,
+    columns: ColumnBuffer,
+    written: usize,
+}
+
+impl DeviceColumns {
+    fn new(group: hdf5::Group, field_chars: Vec<char>) -> Self {
+        DeviceColumns { group, columns: ColumnBuffer::new(field_chars), written: 0 }
+    }
+
+    fn push_buffer(&mut self, buffer: &RawBuffer) -> Result<(), Hdf5WriteError> {
+        self.columns.push_buffer(buffer);
+
+        if self.columns.len() >= FLUSH_BATCH_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Hdf5WriteError> {
+        if self.columns.is_empty() {
+            return Ok(());
+        }
+
+        append_slice(&self.group, "time", &self.columns.time, self.written)?;
+
+        for (i, (&ch, values)) in self.columns.field_chars.iter().zip(self.columns.fields.iter()).enumerate() {
+            let name = format!("field{i}");
+            append_sample_dataset(&self.group, &name, ch, values, self.written)?;
+        }
+
+        self.written += self.columns.len();
+        self.columns.clear();
+
+        Ok(())
+    }
+}
+
+fn append_sample_dataset(
+    group: &hdf5::Group,
+    name: &str,
+    ch: char,
+    values: &[Sample],
+    offset: usize,
+) -> Result<(), Hdf5WriteError> {
+    match ch {
+        'B' => append_slice(group, name, &map_samples(values, |s| match s { Sample::U8(v) => *v, _ => unreachable!() }), offset),
+        'H' => append_slice(group, name, &map_samples(values, |s| match s { Sample::U16(v) => *v, _ => unreachable!() }), offset),
+        'I' => append_slice(group, name, &map_samples(values, |s| match s { Sample::U32(v) => *v, _ => unreachable!() }), offset),
+        'i' => append_slice(group, name, &map_samples(values, |s| match s { Sample::I32(v) => *v, _ => unreachable!() }), offset),
+        'f' => append_slice(group, name, &map_samples(values, |s| match s { Sample::F32(v) => *v, _ => unreachable!() }), offset),
+        'd' => append_slice(group, name, &map_samples(values, |s| match s { Sample::F64(v) => *v, _ => unreachable!() }), offset),
+        'q' => append_slice(group, name, &map_samples(values, |s| match s { Sample::I64(v) => *v, _ => unreachable!() }), offset),
+        _ => Err(Hdf5WriteError::Message(format!("unsupported format character '{ch}' for dataset '{name}'"))),
+    }
+}
+
+fn map_samples<T>(values: &[Sample], f: impl Fn(&Sample) -> T) -> Vec<T> {
+    values.iter().map(f).collect()
+}
+
+fn append_slice<T: hdf5::H5Type + Copy>(
+    group: &hdf5::Group,
+    name: &str,
+    values: &[T],
+    offset: usize,
+) -> Result<(), Hdf5WriteError> {
+    let dataset = match group.dataset(name) {
+        Ok(dataset) => dataset,
+        Err(_) => group
+            .new_dataset::<T>()
+            .shape(hdf5::SimpleExtents::resizable(vec![0]))
+            .chunk(ndarray::Ix1(FLUSH_BATCH_SIZE))
+            .create(name)?,
+    };
+
+    let new_len = offset + values.len();
+    dataset.resize(ndarray::Ix1(new_len))?;
+    dataset.write_slice(values, offset..new_len)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_buffer_records_time_for_header_only_device() {
+        let mut columns = ColumnBuffer::new(Vec::new());
+
+        columns.push_buffer(&RawBuffer { id: 1, time: 10, samples: Vec::new() });
+        columns.push_buffer(&RawBuffer { id: 1, time: 20, samples: Vec::new() });
+
+        assert_eq!(columns.time, vec![10, 20]);
+        assert!(columns.fields.is_empty());
+        assert_eq!(columns.len(), 2);
+    }
+
+    #[test]
+    fn column_buffer_splits_buffer_into_one_row_per_record() {
+        let mut columns = ColumnBuffer::new(vec!['f']);
+
+        columns.push_buffer(&RawBuffer {
+            id: 1,
+            time: 10,
+            samples: vec![Sample::F32(1.5), Sample::F32(2.5)],
+        });
+
+        assert_eq!(columns.time, vec![10, 10]);
+        assert_eq!(columns.fields, vec![vec![Sample::F32(1.5), Sample::F32(2.5)]]);
+        assert_eq!(columns.len(), 2);
+    }
+
+    #[test]
+    fn column_buffer_clear_resets_all_columns() {
+        let mut columns = ColumnBuffer::new(vec!['f']);
+        columns.push_buffer(&RawBuffer { id: 1, time: 10, samples: vec![Sample::F32(1.5)] });
+        assert!(!columns.is_empty());
+
+        columns.clear();
+
+        assert!(columns.is_empty());
+        assert_eq!(columns.len(), 0);
+        assert!(columns.fields[0].is_empty());
+    }
+}
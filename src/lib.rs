@@ -1,29 +1,27 @@
 use std::cmp;
-use std::error;
-use std::fmt;
+use std::collections::HashMap;
 use std::io::{BufReader, BufRead};
 use std::io;
 use std::path::Path;
 use std::fs::{File, metadata};
 
-const HEADER_MAX_SIZE: usize = 2*1024;     // This refers to the JSON file header. This value comes directly from the tag C++ code.
-
-#[derive(Debug, Clone)]
-pub enum HeaderError {
-    HeaderMissing,
-    ReadLineExceedsSize(usize),
-}
+use serde_json::Value;
+
+mod container;
+mod decode;
+mod error;
+mod hdf5_writer;
+mod merge;
+pub use container::{open_container, write_container, ContainerError, ContainerReader, DeviceStream};
+pub use decode::{
+    decode_all, decode_buffer, decode_stream, decode_stream_with_capacity,
+    BufferStream, Endian, Sample, DEFAULT_MAX_BUFFER_SIZE,
+};
+pub use error::DecodeError;
+pub use hdf5_writer::{write_h5, Hdf5WriteError};
+pub use merge::{merge_by_time, Merge};
 
-impl error::Error for HeaderError {}
-
-impl fmt::Display for HeaderError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            HeaderError::HeaderMissing => write!(f, "Cannot find line to use as the header"),
-            HeaderError::ReadLineExceedsSize(val) => write!(f, "First line of file ({val} characters) exceeds the maximum possible length of the header ({HEADER_MAX_SIZE} characters)"),
-        }
-    }
-}
+const HEADER_MAX_SIZE: usize = 2*1024;     // This refers to the JSON file header. This value comes directly from the tag C++ code.
 
 pub fn import_file(filename: &str) -> Result<BufReader<File>, io::Error> {
     let path = Path::new(filename);
@@ -34,16 +32,16 @@ pub fn import_file(filename: &str) -> Result<BufReader<File>, io::Error> {
     return Ok(BufReader::new(file))
 }
 
-pub fn read_file_header(reader: &mut BufReader<File>) -> Result<String, HeaderError> {
+pub fn read_file_header(reader: &mut BufReader<File>) -> Result<String, DecodeError> {
     let mut line: String = String::new();
     let len: usize = reader.read_line(&mut line)
         .map_err(|_| {
-            HeaderError::HeaderMissing
+            DecodeError::HeaderMissing
         })?;
 
     match len {
         0..=HEADER_MAX_SIZE => Ok(line),
-        _ => Err(HeaderError::ReadLineExceedsSize(len))
+        _ => Err(DecodeError::ReadLineExceedsSize(len))
     }
 }
 
@@ -55,16 +53,54 @@ pub fn get_file_size(filename: &str) -> Result<u64, io::Error> {
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct DeviceFormat {
-    id: u8,
-    data_format: Vec<char>,
-    header_format: Vec<char>,
+    pub(crate) id: u8,
+    pub(crate) data_format: Vec<char>,
+    pub(crate) header_format: Vec<char>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+// `samples` holds f32/f64 values, so derive(Eq) isn't available; Ord below
+// only ever compares `time`, so we assert Eq manually rather than give up
+// ordering (needed for the time sort in `decode_all`).
+#[derive(Debug, PartialEq)]
 pub struct RawBuffer {
-    id: u8,
-    time: u32,
-    data: Vec<u8>,
+    pub(crate) id: u8,
+    pub(crate) time: u32,
+    pub(crate) samples: Vec<Sample>,
+}
+
+impl Eq for RawBuffer {}
+
+/// Reads the header's `buffers` object into one [`DeviceFormat`] per
+/// device id. Each entry is expected to look like
+/// `{"<id>": {"header_format": "<BI", "data_format": "<fff"}}`.
+pub fn parse_device_formats(header: &Value) -> Result<HashMap<u8, DeviceFormat>, DecodeError> {
+    let buffers = header["buffers"]
+        .as_object()
+        .ok_or_else(|| DecodeError::MalformedBuffers("missing 'buffers' object".to_string()))?;
+
+    let mut formats = HashMap::with_capacity(buffers.len());
+
+    for (key, value) in buffers {
+        let id: u8 = key
+            .parse()
+            .map_err(|_| DecodeError::MalformedBuffers(format!("device id '{key}' is not a u8")))?;
+
+        let header_format = value["header_format"]
+            .as_str()
+            .ok_or_else(|| DecodeError::MalformedBuffers(format!("device {id} is missing 'header_format'")))?
+            .chars()
+            .collect();
+
+        let data_format = value["data_format"]
+            .as_str()
+            .ok_or_else(|| DecodeError::MalformedBuffers(format!("device {id} is missing 'data_format'")))?
+            .chars()
+            .collect();
+
+        formats.insert(id, DeviceFormat { id, data_format, header_format });
+    }
+
+    Ok(formats)
 }
 
 impl Ord for RawBuffer {
@@ -88,13 +124,13 @@ mod tests {
         let buffer1 = RawBuffer {
             id: 1,
             time: 10,
-            data: [0, 1, 2, 3].to_vec(),
+            samples: vec![Sample::U8(0), Sample::U8(1), Sample::U8(2), Sample::U8(3)],
         };
 
         let buffer2 = RawBuffer {
             id: 1,
             time: 15,
-            data: [0, 1, 2, 3].to_vec(),
+            samples: vec![Sample::U8(0), Sample::U8(1), Sample::U8(2), Sample::U8(3)],
         };
 
         assert!(buffer1 <= buffer2);
@@ -105,13 +141,13 @@ mod tests {
         let buffer1 = RawBuffer {
             id: 1,
             time: 10,
-            data: [0, 1, 2, 3].to_vec(),
+            samples: vec![Sample::U8(0), Sample::U8(1), Sample::U8(2), Sample::U8(3)],
         };
 
         let buffer2 = RawBuffer {
             id: 1,
             time: 15,
-            data: [0, 1, 2, 3].to_vec(),
+            samples: vec![Sample::U8(0), Sample::U8(1), Sample::U8(2), Sample::U8(3)],
         };
 
         assert!(buffer2 >= buffer1)
@@ -122,13 +158,13 @@ mod tests {
         let buffer1 = RawBuffer {
             id: 1,
             time: 10,
-            data: [0, 1, 2, 3].to_vec(),
+            samples: vec![Sample::U8(0), Sample::U8(1), Sample::U8(2), Sample::U8(3)],
         };
 
         let buffer2 = RawBuffer {
             id: 1,
             time: 10,
-            data: [0, 1, 2, 3].to_vec(),
+            samples: vec![Sample::U8(0), Sample::U8(1), Sample::U8(2), Sample::U8(3)],
         };
 
         assert_eq!(buffer1, buffer2);
@@ -139,13 +175,13 @@ mod tests {
         let buffer1 = RawBuffer {
             id: 1,
             time: 10,
-            data: [0, 1, 2, 3].to_vec(),
+            samples: vec![Sample::U8(0), Sample::U8(1), Sample::U8(2), Sample::U8(3)],
         };
 
         let buffer2 = RawBuffer {
             id: 1,
             time: 15,
-            data: [0, 1, 2, 3].to_vec(),
+            samples: vec![Sample::U8(0), Sample::U8(1), Sample::U8(2), Sample::U8(3)],
         };
 
         assert!(buffer1 != buffer2);
@@ -1,7 +1,10 @@
 use clap::Parser;
 use serde_json::Value;
+use std::path::Path;
 use std::process;
-use mtag_deserializinator_inator::{import_file, get_file_size, read_file_header};
+use mtag_deserializinator_inator::{
+    decode_all, get_file_size, import_file, parse_device_formats, read_file_header, write_h5,
+};
 use colored::Colorize;
 
 /// Program to parse and correct the binary output data of the MTAG 2.0 and related tags
@@ -56,4 +59,26 @@ fn main() {
         .unwrap_or_else(|| {println!("{} {}", "Error: ".red().bold(), "Malformed header does not contain 'buffers' as key: ".red()); process::exit(4);}) {
         println!("{:#?}: {:#?}", key, value);
     }
+
+    let formats = parse_device_formats(&parsed_header)
+        .unwrap_or_else(|err| {
+            println!("{} {} {}", "Error: ".red().bold(), "Failed to parse device formats: ".red(), err.to_string().red());
+            process::exit(5);
+        });
+
+    let buffers = decode_all(&mut reader, &formats)
+        .unwrap_or_else(|err| {
+            println!("{} {} {}", "Error: ".red().bold(), "Failed to decode buffers: ".red(), err.to_string().red());
+            process::exit(6);
+        });
+
+    println!("Decoded {} buffer(s)", buffers.len());
+
+    write_h5(Path::new(&args.destination), &line, &formats, buffers)
+        .unwrap_or_else(|err| {
+            println!("{} {} {}", "Error: ".red().bold(), "Failed to write HDF5 output: ".red(), err.to_string().red());
+            process::exit(7);
+        });
+
+    println!("Wrote {}", args.destination);
 }
@@ -0,0 +1,106 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use crate::RawBuffer;
+
+/// One stream's next pending buffer, paired with the rest of that stream.
+/// Ordered by `(time, id)` so ties on `time` break deterministically on
+/// device id rather than on stream arrival order.
+struct HeapEntry<I> {
+    buffer: RawBuffer,
+    iter: I,
+}
+
+impl<I> PartialEq for HeapEntry<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.buffer.time == other.buffer.time && self.buffer.id == other.buffer.id
+    }
+}
+
+impl<I> Eq for HeapEntry<I> {}
+
+impl<I> PartialOrd for HeapEntry<I> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I> Ord for HeapEntry<I> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.buffer
+            .time
+            .cmp(&other.buffer.time)
+            .then_with(|| self.buffer.id.cmp(&other.buffer.id))
+    }
+}
+
+/// Iterator returned by [`merge_by_time`].
+pub struct Merge<I> {
+    heap: BinaryHeap<Reverse<HeapEntry<I>>>,
+}
+
+impl<I: Iterator<Item = RawBuffer>> Iterator for Merge<I> {
+    type Item = RawBuffer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(HeapEntry { buffer, mut iter }) = self.heap.pop()?;
+
+        if let Some(next_buffer) = iter.next() {
+            self.heap.push(Reverse(HeapEntry { buffer: next_buffer, iter }));
+        }
+
+        Some(buffer)
+    }
+}
+
+/// Merges several device streams, each already in time order, into a
+/// single time-ordered stream.
+///
+/// Keeps only the next pending buffer from each input stream on a
+/// [`BinaryHeap`] (wrapped in [`Reverse`] so it behaves as a min-heap by
+/// `time`), giving O(N log k) overall for `N` total buffers across `k`
+/// streams. Ties on `time` are broken by device `id` so the merge order is
+/// deterministic regardless of the order `streams` is given in.
+pub fn merge_by_time<I: Iterator<Item = RawBuffer>>(streams: Vec<I>) -> Merge<I> {
+    let mut heap = BinaryHeap::with_capacity(streams.len());
+
+    for mut iter in streams {
+        if let Some(buffer) = iter.next() {
+            heap.push(Reverse(HeapEntry { buffer, iter }));
+        }
+    }
+
+    Merge { heap }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sample;
+
+    fn buffer(id: u8, time: u32) -> RawBuffer {
+        RawBuffer { id, time, samples: vec![Sample::U8(0)] }
+    }
+
+    #[test]
+    fn merge_by_time_interleaves_streams() {
+        let a = vec![buffer(1, 10), buffer(1, 30)].into_iter();
+        let b = vec![buffer(2, 20), buffer(2, 40)].into_iter();
+
+        let merged: Vec<RawBuffer> = merge_by_time(vec![a, b]).collect();
+        let times: Vec<u32> = merged.iter().map(|buffer| buffer.time).collect();
+
+        assert_eq!(times, vec![10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn merge_by_time_breaks_ties_on_device_id() {
+        let a = vec![buffer(2, 10)].into_iter();
+        let b = vec![buffer(1, 10)].into_iter();
+
+        let merged: Vec<RawBuffer> = merge_by_time(vec![a, b]).collect();
+
+        assert_eq!(merged[0].id, 1);
+        assert_eq!(merged[1].id, 2);
+    }
+}